@@ -1,6 +1,8 @@
 use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
 use std::fmt;
 use std::io;
+use std::sync::Once;
 
 use super::Id;
 use crate::util::SyncWrapper;
@@ -13,28 +15,288 @@ cfg_rt! {
 }
 
 enum Repr {
-    Cancelled,
-    Panic(SyncWrapper<Box<dyn Any + Send + 'static>>),
+    Cancelled(CancelCause),
+    Panic(SyncWrapper<Box<dyn Any + Send + 'static>>, PanicInfo),
+}
+
+/// The reason a task was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CancelCause {
+    /// The task was cancelled by an explicit call to [`JoinHandle::abort`]
+    /// or [`AbortHandle::abort`].
+    ///
+    /// [`JoinHandle::abort`]: crate::task::JoinHandle::abort
+    /// [`AbortHandle::abort`]: crate::task::AbortHandle::abort
+    Aborted,
+    /// The task was dropped without completing because the runtime it was
+    /// spawned on shut down.
+    RuntimeShutdown,
+    /// The task was cancelled by some other caller, such as a remote peer
+    /// in a distributed runtime.
+    Remote,
+}
+
+/// The source location of a captured panic.
+///
+/// This mirrors [`std::panic::Location`], but owns its file name instead of
+/// borrowing it: the panic hook only gives us a `Location` borrowed for the
+/// duration of the hook call, so we copy the parts we need out of it to
+/// outlive the hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicLocation {
+    file: String,
+    line: u32,
+    column: u32,
+}
+
+impl PanicLocation {
+    /// Returns the name of the source file from which the panic originated.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Returns the line number from which the panic originated.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the column from which the panic originated.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl fmt::Display for PanicLocation {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+impl From<&std::panic::Location<'_>> for PanicLocation {
+    fn from(loc: &std::panic::Location<'_>) -> Self {
+        PanicLocation {
+            file: loc.file().to_owned(),
+            line: loc.line(),
+            column: loc.column(),
+        }
+    }
+}
+
+/// The location and backtrace captured at the moment a task panicked.
+///
+/// Both fields are best-effort: the location is only available if the panic
+/// went through our hook (true for a `panic!` during task polling, but not
+/// for `std::panic::resume_unwind`, which skips the hook), and the backtrace
+/// is only captured when `RUST_BACKTRACE` is set, since capturing one is not
+/// free.
+struct PanicInfo {
+    location: Option<PanicLocation>,
+    backtrace: Option<Backtrace>,
+}
+
+thread_local! {
+    // Filled in by the panic hook installed by `ensure_panic_hook_installed`,
+    // and drained by `take_captured_panic_info` once the panic has
+    // unwound back into `JoinError::panic`. Thread-local because the panic
+    // hook itself is process-wide, but a panic can only unwind on the
+    // thread it occurred on, so there is no cross-task interference.
+    static CAPTURED_PANIC_INFO: std::cell::Cell<Option<(PanicLocation, Option<Backtrace>)>> =
+        const { std::cell::Cell::new(None) };
+}
+
+fn take_captured_panic_info() -> (Option<PanicLocation>, Option<Backtrace>) {
+    match CAPTURED_PANIC_INFO.with(|cell| cell.take()) {
+        Some((location, backtrace)) => (Some(location), backtrace),
+        None => (None, None),
+    }
+}
+
+/// Installs a panic hook, once per process, that records the location and
+/// (if `RUST_BACKTRACE` is set) the backtrace of every panic into
+/// `CAPTURED_PANIC_INFO` before chaining into whatever hook was previously
+/// registered.
+///
+/// This hook is installed once and left in place for the lifetime of the
+/// process, rather than swapped in and out around each poll: `set_hook` and
+/// `take_hook` operate on a single global slot, so swapping it per call would
+/// race across worker threads polling tasks concurrently, with one thread's
+/// restore able to clobber another thread's still-active hook.
+//
+// Not yet called from the runtime's poll loop: that call site lives in
+// runtime/task/harness.rs, which is not part of this source snapshot. Until
+// it lands there, allow this to go unused outside of the tests here.
+#[cfg_attr(not(test), allow(dead_code))]
+fn ensure_panic_hook_installed() {
+    static INSTALL_HOOK: Once = Once::new();
+    INSTALL_HOOK.call_once(|| {
+        let prev_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let location = info.location().map(PanicLocation::from);
+            let backtrace =
+                Some(Backtrace::capture()).filter(|bt| bt.status() == BacktraceStatus::Captured);
+            CAPTURED_PANIC_INFO.with(|cell| cell.set(location.map(|l| (l, backtrace))));
+            prev_hook(info);
+        }));
+    });
+}
+
+/// Polls a task by calling `f`, converting a panic into a `JoinError` that
+/// carries the panicking location and (if captured) backtrace.
+///
+/// This is the entry point the runtime's task harness should call instead of
+/// invoking `catch_unwind` directly around a poll, so that the panic hook
+/// used to capture location/backtrace information is guaranteed to be
+/// installed first.
+//
+// See the note on `ensure_panic_hook_installed`: the harness call site that
+// would make this reachable from production code is not part of this
+// snapshot, so it is currently only exercised by the tests below.
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) fn poll_with_panic_handling<F, R>(id: Id, f: F) -> Result<R, JoinError>
+where
+    F: FnOnce() -> R + std::panic::UnwindSafe,
+{
+    ensure_panic_hook_installed();
+    // Discard whatever an earlier panic on this thread left behind without
+    // being turned into a `JoinError`, so it is not attributed to this task.
+    CAPTURED_PANIC_INFO.with(|cell| cell.set(None));
+    std::panic::catch_unwind(f).map_err(|err| JoinError::panic(id, err))
 }
 
 impl JoinError {
+    /// Builds a `JoinError` for a cancelled task, reporting
+    /// [`CancelCause::Aborted`] as the cause. Every caller gets that cause,
+    /// including one that cancels a task for another reason such as runtime
+    /// shutdown; `cancelled_with_cause` takes the cause explicitly.
     pub(crate) fn cancelled(id: Id) -> JoinError {
+        JoinError::cancelled_with_cause(id, CancelCause::Aborted)
+    }
+
+    /// Builds a `JoinError` for a task cancelled for `cause`.
+    //
+    // Not yet called from the places that would report a more specific
+    // cause (the runtime-shutdown path, remote cancellation): those call
+    // sites live outside this source snapshot. Until they're updated to
+    // call this instead of `cancelled`, allow it to go unused outside of
+    // the tests here.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) fn cancelled_with_cause(id: Id, cause: CancelCause) -> JoinError {
         JoinError {
-            repr: Repr::Cancelled,
+            repr: Repr::Cancelled(cause),
             id,
         }
     }
 
     pub(crate) fn panic(id: Id, err: Box<dyn Any + Send + 'static>) -> JoinError {
+        let (location, backtrace) = take_captured_panic_info();
         JoinError {
-            repr: Repr::Panic(SyncWrapper::new(err)),
+            repr: Repr::Panic(
+                SyncWrapper::new(err),
+                PanicInfo {
+                    location,
+                    backtrace,
+                },
+            ),
             id,
         }
     }
 
+    /// Returns the backtrace captured at the moment the task panicked, if
+    /// the task terminated due to a panic and `RUST_BACKTRACE` was set.
+    ///
+    /// Capturing a backtrace is relatively expensive, so it is only done
+    /// when `RUST_BACKTRACE` is enabled. Returns `None` if the task did not
+    /// panic, or if the backtrace was not captured.
+    ///
+    /// **Note**: This is an [unstable API][unstable]. The public API of this
+    /// type may break in 1.x releases. See [the documentation on unstable
+    /// features][unstable] for details.
+    ///
+    /// [unstable]: crate#unstable-features
+    #[cfg(tokio_unstable)]
+    #[cfg_attr(docsrs, doc(cfg(tokio_unstable)))]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match &self.repr {
+            Repr::Panic(_, info) => info.backtrace.as_ref(),
+            Repr::Cancelled(_) => None,
+        }
+    }
+
+    /// Returns the source location of the panic that caused the task to
+    /// fail, if the task terminated due to a panic.
+    ///
+    /// **Note**: This is an [unstable API][unstable]. The public API of this
+    /// type may break in 1.x releases. See [the documentation on unstable
+    /// features][unstable] for details.
+    ///
+    /// [unstable]: crate#unstable-features
+    #[cfg(tokio_unstable)]
+    #[cfg_attr(docsrs, doc(cfg(tokio_unstable)))]
+    pub fn location(&self) -> Option<&PanicLocation> {
+        match &self.repr {
+            Repr::Panic(_, info) => info.location.as_ref(),
+            Repr::Cancelled(_) => None,
+        }
+    }
+
+    /// Returns the string payload of the panic that caused the task to
+    /// fail, if the task terminated due to a panic and the payload is a
+    /// `&'static str` or `String`, the two shapes produced by the `panic!`
+    /// macro.
+    ///
+    /// Unlike [`into_panic`] and [`try_into_panic`], this does not consume
+    /// the `JoinError`, so it is suitable for logging a panicking task's
+    /// message without giving up the ability to `resume_unwind` later.
+    ///
+    /// [`into_panic`]: JoinError::into_panic
+    /// [`try_into_panic`]: JoinError::try_into_panic
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let err = tokio::spawn(async {
+    ///         panic!("boom");
+    ///     }).await.unwrap_err();
+    ///
+    ///     assert_eq!(err.panic_message(), Some("boom"));
+    /// }
+    /// ```
+    pub fn panic_message(&self) -> Option<&str> {
+        match &self.repr {
+            Repr::Panic(payload, _) => {
+                let payload = payload.get_ref();
+                if let Some(msg) = payload.downcast_ref::<&'static str>() {
+                    Some(msg)
+                } else {
+                    payload.downcast_ref::<String>().map(String::as_str)
+                }
+            }
+            Repr::Cancelled(_) => None,
+        }
+    }
+
     /// Returns true if the error was caused by the task being cancelled.
     pub fn is_cancelled(&self) -> bool {
-        matches!(&self.repr, Repr::Cancelled)
+        matches!(&self.repr, Repr::Cancelled(_))
+    }
+
+    /// Returns the cause of cancellation, if the task was cancelled.
+    ///
+    /// **Note**: This is an [unstable API][unstable]. The public API of this
+    /// type may break in 1.x releases. See [the documentation on unstable
+    /// features][unstable] for details.
+    ///
+    /// [unstable]: crate#unstable-features
+    #[cfg(tokio_unstable)]
+    #[cfg_attr(docsrs, doc(cfg(tokio_unstable)))]
+    pub fn cancel_cause(&self) -> Option<CancelCause> {
+        match &self.repr {
+            Repr::Cancelled(cause) => Some(*cause),
+            Repr::Panic(_, _) => None,
+        }
     }
 
     /// Returns true if the error was caused by the task panicking.
@@ -54,7 +316,7 @@ impl JoinError {
     /// }
     /// ```
     pub fn is_panic(&self) -> bool {
-        matches!(&self.repr, Repr::Panic(_))
+        matches!(&self.repr, Repr::Panic(_, _))
     }
 
     /// Consumes the join error, returning the object with which the task panicked.
@@ -111,7 +373,7 @@ impl JoinError {
     /// ```
     pub fn try_into_panic(self) -> Result<Box<dyn Any + Send + 'static>, JoinError> {
         match self.repr {
-            Repr::Panic(p) => Ok(p.into_inner()),
+            Repr::Panic(p, _) => Ok(p.into_inner()),
             _ => Err(self),
         }
     }
@@ -135,8 +397,8 @@ impl JoinError {
 impl fmt::Display for JoinError {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.repr {
-            Repr::Cancelled => write!(fmt, "task {} was cancelled", self.id),
-            Repr::Panic(_) => write!(fmt, "task {} panicked", self.id),
+            Repr::Cancelled(_) => write!(fmt, "task {} was cancelled", self.id),
+            Repr::Panic(_, _) => write!(fmt, "task {} panicked", self.id),
         }
     }
 }
@@ -144,21 +406,43 @@ impl fmt::Display for JoinError {
 impl fmt::Debug for JoinError {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.repr {
-            Repr::Cancelled => write!(fmt, "JoinError::Cancelled({:?})", self.id),
-            Repr::Panic(_) => write!(fmt, "JoinError::Panic({:?}, ...)", self.id),
+            Repr::Cancelled(cause) => {
+                write!(fmt, "JoinError::Cancelled({:?}, {:?})", self.id, cause)
+            }
+            Repr::Panic(_, _) => write!(fmt, "JoinError::Panic({:?}, ...)", self.id),
         }
     }
 }
 
-impl std::error::Error for JoinError {}
+impl std::error::Error for JoinError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // If a task panicked with a boxed error (a payload of type
+        // `Box<dyn Error + Send + Sync>` or `Box<dyn Error + Send>`), expose
+        // it as the source so that `anyhow`/`eyre`-style `?`-based reporting
+        // can walk the chain down to the original error instead of stopping
+        // at `JoinError`. A payload of a concrete error type, such as
+        // `panic_any(my_error)`, is not recognised and gives `None`.
+        let Repr::Panic(payload, _) = &self.repr else {
+            return None;
+        };
+        let payload = payload.get_ref();
+        if let Some(err) = payload.downcast_ref::<Box<dyn std::error::Error + Send + Sync>>() {
+            Some(&**err)
+        } else {
+            payload
+                .downcast_ref::<Box<dyn std::error::Error + Send>>()
+                .map(|err| &**err as &(dyn std::error::Error + 'static))
+        }
+    }
+}
 
 impl From<JoinError> for io::Error {
     fn from(src: JoinError) -> io::Error {
         io::Error::new(
             io::ErrorKind::Other,
             match src.repr {
-                Repr::Cancelled => "task was cancelled",
-                Repr::Panic(_) => "task panicked",
+                Repr::Cancelled(_) => "task was cancelled",
+                Repr::Panic(_, _) => "task panicked",
             },
         )
     }
@@ -172,13 +456,27 @@ cfg_rt! {
     }
 }
 
-#[derive(Debug)]
 pub(crate) enum SpawnErrorKind {
     /// Pool is shutting down and the task was not scheduled
     Shutdown,
     /// There are no worker threads available to take the task
     /// and the OS failed to spawn a new one
     NoBlockingThreads(io::Error),
+    /// The task was rejected because a bounded queue was at capacity. The
+    /// task itself is kept around so the caller can retry or shed it.
+    QueueFull(SyncWrapper<Box<dyn Any + Send>>),
+}
+
+impl fmt::Debug for SpawnErrorKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnErrorKind::Shutdown => fmt.debug_tuple("Shutdown").finish(),
+            SpawnErrorKind::NoBlockingThreads(e) => {
+                fmt.debug_tuple("NoBlockingThreads").field(e).finish()
+            }
+            SpawnErrorKind::QueueFull(_) => fmt.debug_tuple("QueueFull").finish(),
+        }
+    }
 }
 
 impl SpawnError {
@@ -194,6 +492,12 @@ impl SpawnError {
         }
     }
 
+    pub(crate) fn at_capacity<T: Send + 'static>(task: T) -> Self {
+        Self {
+            kind: SpawnErrorKind::QueueFull(SyncWrapper::new(Box::new(task))),
+        }
+    }
+
     /// Returns `true` if the error was caused by the runtime being shutdown.
     pub fn is_shutdown(&self) -> bool {
         matches!(&self.kind, SpawnErrorKind::Shutdown)
@@ -204,6 +508,26 @@ impl SpawnError {
     pub fn is_no_blocking_threads(&self) -> bool {
         matches!(&self.kind, SpawnErrorKind::NoBlockingThreads(_))
     }
+
+    /// Returns `true` if the error was caused by a bounded queue being at
+    /// capacity.
+    pub fn is_at_capacity(&self) -> bool {
+        matches!(&self.kind, SpawnErrorKind::QueueFull(_))
+    }
+
+    /// Consumes the error, recovering the task that was rejected due to
+    /// backpressure.
+    ///
+    /// Returns `None` if the error was not caused by the queue being at
+    /// capacity, or if `T` does not match the type of the rejected task.
+    /// This lets callers implement their own retry or shed-load policy
+    /// instead of losing the work outright.
+    pub fn into_inner<T: Send + 'static>(self) -> Option<T> {
+        match self.kind {
+            SpawnErrorKind::QueueFull(task) => task.into_inner().downcast::<T>().ok().map(|b| *b),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for SpawnError {
@@ -213,6 +537,7 @@ impl fmt::Display for SpawnError {
             SpawnErrorKind::NoBlockingThreads(_) => {
                 fmt.write_str("unable to spawn blocking thread")
             }
+            SpawnErrorKind::QueueFull(_) => fmt.write_str("task queue is at capacity"),
         }
     }
 }
@@ -222,6 +547,7 @@ impl std::error::Error for SpawnError {
         match &self.kind {
             SpawnErrorKind::Shutdown => None,
             SpawnErrorKind::NoBlockingThreads(e) => Some(e),
+            SpawnErrorKind::QueueFull(_) => None,
         }
     }
 }
@@ -233,6 +559,114 @@ impl From<SpawnError> for io::Error {
                 io::Error::new(io::ErrorKind::Other, "runtime shutting down")
             }
             SpawnErrorKind::NoBlockingThreads(e) => e,
+            SpawnErrorKind::QueueFull(_) => {
+                io::Error::new(io::ErrorKind::WouldBlock, "task queue is at capacity")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll_with_panic_handling_converts_panic_to_join_error() {
+        let id = Id::next();
+        let err = poll_with_panic_handling(id, || -> () { panic!("boom") }).unwrap_err();
+
+        assert!(err.is_panic());
+        assert_eq!(err.panic_message(), Some("boom"));
+    }
+
+    #[cfg(tokio_unstable)]
+    #[test]
+    fn poll_with_panic_handling_captures_location() {
+        let id = Id::next();
+        let err = poll_with_panic_handling(id, || -> () { panic!("boom") }).unwrap_err();
+
+        let location = err.location().expect("location should be captured");
+        assert!(location.file().ends_with("error.rs"));
+    }
+
+    #[test]
+    fn panic_message_handles_str_and_string_payloads() {
+        let str_err = JoinError::panic(Id::next(), Box::new("boom"));
+        assert_eq!(str_err.panic_message(), Some("boom"));
+
+        let string_err = JoinError::panic(Id::next(), Box::new(String::from("boom")));
+        assert_eq!(string_err.panic_message(), Some("boom"));
+
+        let other_err = JoinError::panic(Id::next(), Box::new(42_i32));
+        assert_eq!(other_err.panic_message(), None);
+    }
+
+    #[test]
+    fn cancelled_with_cause_is_cancelled() {
+        for cause in [
+            CancelCause::Aborted,
+            CancelCause::RuntimeShutdown,
+            CancelCause::Remote,
+        ] {
+            let err = JoinError::cancelled_with_cause(Id::next(), cause);
+            assert!(err.is_cancelled());
         }
+
+        assert!(JoinError::cancelled(Id::next()).is_cancelled());
+    }
+
+    #[cfg(tokio_unstable)]
+    #[test]
+    fn cancel_cause_round_trips_through_cancelled_with_cause() {
+        for cause in [
+            CancelCause::Aborted,
+            CancelCause::RuntimeShutdown,
+            CancelCause::Remote,
+        ] {
+            let err = JoinError::cancelled_with_cause(Id::next(), cause);
+            assert_eq!(err.cancel_cause(), Some(cause));
+        }
+
+        assert_eq!(
+            JoinError::cancelled(Id::next()).cancel_cause(),
+            Some(CancelCause::Aborted)
+        );
+
+        let panic_err = JoinError::panic(Id::next(), Box::new("boom"));
+        assert_eq!(panic_err.cancel_cause(), None);
+    }
+
+    #[test]
+    fn source_exposes_boxed_error_payloads() {
+        use std::error::Error as _;
+
+        let io_err: Box<dyn std::error::Error + Send + Sync> =
+            Box::new(io::Error::new(io::ErrorKind::Other, "disk on fire"));
+        let err = JoinError::panic(Id::next(), Box::new(io_err));
+        assert_eq!(
+            err.source().map(|e| e.to_string()),
+            Some("disk on fire".to_string())
+        );
+
+        let non_error_err = JoinError::panic(Id::next(), Box::new("boom"));
+        assert!(non_error_err.source().is_none());
+    }
+
+    #[test]
+    fn queue_full_round_trips_the_rejected_task() {
+        let err = SpawnError::at_capacity(String::from("rejected task"));
+        assert!(err.is_at_capacity());
+        assert!(!err.is_shutdown());
+        assert_eq!(
+            err.into_inner::<String>(),
+            Some(String::from("rejected task"))
+        );
+
+        let wrong_type_err = SpawnError::at_capacity(String::from("rejected task"));
+        assert_eq!(wrong_type_err.into_inner::<u32>(), None);
+
+        let shutdown_err = SpawnError::shutdown();
+        assert!(!shutdown_err.is_at_capacity());
+        assert_eq!(shutdown_err.into_inner::<String>(), None);
     }
 }
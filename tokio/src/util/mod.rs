@@ -0,0 +1,2 @@
+mod sync_wrapper;
+pub(crate) use sync_wrapper::SyncWrapper;
@@ -0,0 +1,27 @@
+// A wrapper that can make any type Sync, because we guarantee that the inner
+// value is only ever accessed when the wrapper is owned or mutably borrowed.
+// This is used to store !Sync payloads (such as `Box<dyn Any + Send>`) inside
+// types like `JoinError` that must themselves be `Sync`.
+pub(crate) struct SyncWrapper<T> {
+    value: T,
+}
+
+impl<T> SyncWrapper<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    pub(crate) fn into_inner(self) -> T {
+        self.value
+    }
+
+    pub(crate) fn get_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+// Safety: `T` is only ever accessed through `&self`/`self`, which requires
+// the caller to hold an exclusive or shared reference to the `SyncWrapper`
+// itself, so it is not possible to access the same value concurrently from
+// two threads even when `T` is not `Sync`.
+unsafe impl<T> Sync for SyncWrapper<T> {}